@@ -73,10 +73,12 @@ pub struct Sender(spsc::Sender<Request>);
 #[wasm_bindgen]
 impl Sender {
     pub fn init(&self) -> Result<(), JsValue> {
-        self.0.send(&Request::Init)
+        self.0.send(Request::Init)?;
+        Ok(())
     }
 
     pub fn done(&self, count: u32) -> Result<(), JsValue> {
-        self.0.send(&Request::Done { count })
+        self.0.send(Request::Done { count })?;
+        Ok(())
     }
 }
@@ -6,9 +6,12 @@
 //!
 //! This allows us to deploy Rust code as a worker process communicating with the main thread.
 use js_sys::Uint8Array;
+use std::fmt;
 use thiserror::Error;
 use wasm_bindgen::prelude::*;
 
+pub mod mpsc;
+pub mod select;
 pub mod spsc;
 
 /// [`Shareable::from`] indicates that it needs at least `n` bytes to proceed
@@ -16,6 +19,151 @@ pub mod spsc;
 #[error("expects {0} bytes more")]
 pub struct Expects(pub u32);
 
+/// Error returned by a failed `send`, carrying the value back so the caller can recover it
+/// (e.g. to retry once space frees up) instead of losing it.
+///
+/// Mirrors the shape of [`std::sync::mpsc::SendError`]/[`std::sync::mpsc::TrySendError`]. Not
+/// derived via `#[derive(Debug)]` so that recovering the value doesn't force `T: Debug`, same as
+/// the standard library's own `SendError`.
+pub enum SendError<T> {
+    /// The channel didn't have enough free space for the value.
+    Full(T),
+    /// The value failed to serialize into bytes.
+    Serialize(T, Box<dyn std::error::Error>),
+}
+
+impl<T> SendError<T> {
+    /// Recovers the value that failed to send.
+    pub fn into_inner(self) -> T {
+        match self {
+            SendError::Full(value) => value,
+            SendError::Serialize(value, _) => value,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full(_) => write!(f, "SendError::Full(..)"),
+            SendError::Serialize(_, error) => write!(f, "SendError::Serialize(.., {:?})", error),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full(_) => write!(f, "not enough space"),
+            SendError::Serialize(_, error) => write!(f, "serialization error: {}", error),
+        }
+    }
+}
+
+impl<T> std::error::Error for SendError<T> {}
+
+impl<T> From<SendError<T>> for JsValue {
+    fn from(error: SendError<T>) -> JsValue {
+        error.to_string().into()
+    }
+}
+
+/// Error returned by a blocking, back-pressured send, such as [`spsc::Sender::send_blocking`]
+///
+/// Like [`SendError`], carries the value back so the caller can recover it. Not derived via
+/// `#[derive(Debug)]` so that recovering the value doesn't force `T: Debug`.
+pub enum SendTimeoutError<T> {
+    /// No space freed up before the timeout elapsed.
+    Timeout(T),
+    /// The value failed to serialize into bytes.
+    Serialize(T, Box<dyn std::error::Error>),
+}
+
+impl<T> SendTimeoutError<T> {
+    /// Recovers the value that failed to send.
+    pub fn into_inner(self) -> T {
+        match self {
+            SendTimeoutError::Timeout(value) => value,
+            SendTimeoutError::Serialize(value, _) => value,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => write!(f, "SendTimeoutError::Timeout(..)"),
+            SendTimeoutError::Serialize(_, error) => {
+                write!(f, "SendTimeoutError::Serialize(.., {:?})", error)
+            }
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => write!(f, "timed out waiting for space"),
+            SendTimeoutError::Serialize(_, error) => write!(f, "serialization error: {}", error),
+        }
+    }
+}
+
+impl<T> std::error::Error for SendTimeoutError<T> {}
+
+impl<T> From<SendTimeoutError<T>> for JsValue {
+    fn from(error: SendTimeoutError<T>) -> JsValue {
+        error.to_string().into()
+    }
+}
+
+/// Error returned by a non-blocking receive, such as [`spsc::Receiver::try_recv`]
+#[derive(Debug, Error)]
+pub enum RecvError {
+    /// The channel has no message ready right now
+    #[error("channel is empty")]
+    Empty,
+    /// A message was available but failed to deserialize
+    #[error("deserialization error: {0}")]
+    Deserialize(String),
+}
+
+impl From<JsValue> for RecvError {
+    fn from(error: JsValue) -> RecvError {
+        RecvError::Deserialize(format!("{:?}", error))
+    }
+}
+
+impl From<RecvError> for JsValue {
+    fn from(error: RecvError) -> JsValue {
+        error.to_string().into()
+    }
+}
+
+/// Error returned by a blocking receive with a timeout, such as
+/// [`spsc::Receiver::recv_timeout`]
+#[derive(Debug, Error)]
+pub enum RecvTimeoutError {
+    /// No message arrived before the timeout elapsed
+    #[error("timed out waiting for a value")]
+    Timeout,
+    /// A message was available but failed to deserialize
+    #[error("deserialization error: {0}")]
+    Deserialize(String),
+}
+
+impl From<JsValue> for RecvTimeoutError {
+    fn from(error: JsValue) -> RecvTimeoutError {
+        RecvTimeoutError::Deserialize(format!("{:?}", error))
+    }
+}
+
+impl From<RecvTimeoutError> for JsValue {
+    fn from(error: RecvTimeoutError) -> JsValue {
+        error.to_string().into()
+    }
+}
+
 /// Any type that can be sent through a shared channel must implement this
 pub trait Shareable: Sized {
     /// A generic error
@@ -31,11 +179,13 @@ impl<T> Shareable for T
 where
     for<'a> T: serde::Serialize + serde::Deserialize<'a>,
 {
-    #[cfg(not(any(feature = "serde-bincode")))]
-    std::compile_error!("one of these features has to be enabled: serde-bincode");
+    #[cfg(not(any(feature = "serde-bincode", feature = "serde-postcard")))]
+    std::compile_error!("one of these features has to be enabled: serde-bincode, serde-postcard");
 
     #[cfg(feature = "serde-bincode")]
     type Error = bincode::Error;
+    #[cfg(all(feature = "serde-postcard", not(feature = "serde-bincode")))]
+    type Error = postcard::Error;
 
     fn to_bytes(&self) -> Result<Uint8Array, Self::Error> {
         #[cfg(feature = "serde-bincode")]
@@ -44,6 +194,12 @@ where
             .into();
         #[cfg(feature = "serde-bincode")]
         let mut encoded: Vec<u8> = bincode::serialize(self)?;
+
+        #[cfg(all(feature = "serde-postcard", not(feature = "serde-bincode")))]
+        let mut encoded: Vec<u8> = postcard::to_allocvec(self)?;
+        #[cfg(all(feature = "serde-postcard", not(feature = "serde-bincode")))]
+        let mut result: Vec<u8> = (encoded.len() as u32).to_ne_bytes().into();
+
         result.append(&mut encoded);
         Ok(Uint8Array::from(&result[..]))
     }
@@ -60,11 +216,57 @@ where
             }
             #[cfg(feature = "serde-bincode")]
             return Ok(Ok(bincode::deserialize::<Self>(&data[4..])?));
+            #[cfg(all(feature = "serde-postcard", not(feature = "serde-bincode")))]
+            return Ok(Ok(postcard::from_bytes::<Self>(&data[4..])?));
         }
 
         #[cfg(feature = "serde-bincode")]
-        Err(Box::new(bincode::ErrorKind::Custom(
+        return Err(Box::new(bincode::ErrorKind::Custom(
             "unexpected data".to_string(),
-        )))
+        )));
+        #[cfg(all(feature = "serde-postcard", not(feature = "serde-bincode")))]
+        Err(postcard::Error::SerdeDeCustom)
+    }
+}
+
+/// Like [`Shareable`], but deserializes borrowing directly from the received bytes instead of
+/// allocating a copy of each field (e.g. `&str`/`&[u8]`) out of them.
+///
+/// Implemented for every serde `Deserialize<'a>` type, including ones that borrow from the input
+/// (which [`Shareable`] is not implemented for); give a field `#[serde(borrow)]`
+/// to have it borrow from the bytes [`spsc::Receiver::recv_ref`] returns instead of being copied.
+pub trait ShareableRef<'a>: Sized {
+    /// A generic error
+    type Error: std::error::Error;
+    /// Converts a borrowed byte slice into a value without copying its contents
+    fn from_ref(bytes: &'a [u8]) -> Result<Result<Self, Expects>, Self::Error>;
+}
+
+#[cfg(feature = "serde")]
+impl<'a, T> ShareableRef<'a> for T
+where
+    T: serde::Deserialize<'a>,
+{
+    #[cfg(feature = "serde-bincode")]
+    type Error = bincode::Error;
+    #[cfg(all(feature = "serde-postcard", not(feature = "serde-bincode")))]
+    type Error = postcard::Error;
+
+    fn from_ref(bytes: &'a [u8]) -> Result<Result<Self, Expects>, Self::Error> {
+        if bytes.len() < 4 {
+            return Ok(Err(Expects(4)));
+        }
+        let size = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if (bytes.len() as u32) < 4 + size {
+            return Ok(Err(Expects(4 + size)));
+        }
+        #[cfg(feature = "serde-bincode")]
+        return Ok(Ok(bincode::deserialize::<Self>(
+            &bytes[4..4 + size as usize],
+        )?));
+        #[cfg(all(feature = "serde-postcard", not(feature = "serde-bincode")))]
+        Ok(Ok(postcard::from_bytes::<Self>(
+            &bytes[4..4 + size as usize],
+        )?))
     }
 }
@@ -0,0 +1,472 @@
+//! # Multiple Publisher Single Consumer Channel
+//!
+//! Like [`crate::spsc`], but safe to `send` from several producers at once (for example, several
+//! WebAssembly workers feeding a single consumer over the same `SharedArrayBuffer`).
+//!
+//! `Receiver` here only has the core [`Receiver::recv`]/[`Receiver::try_recv`]/
+//! [`Receiver::recv_timeout`] trio; `spsc::Receiver`'s `recv_async`, `recv_ref` and `recv_batch`
+//! (and `Sender::send_blocking`) haven't been ported over. That's a deliberate scoping decision,
+//! not an oversight: those were added to `spsc` as the primary, actively-developed module, and
+//! porting each to a multi-producer setting deserves its own look at how it interacts with the
+//! CAS-based reservation scheme described below, rather than a copy-paste.
+//!
+// NOTE: `spsc::Sender::send` loads the end offset, copies bytes, then stores the new end offset
+// as three separate non-atomic steps, which is only safe with a single writer. Here, a producer
+// first reserves a byte range with a compare-exchange loop on `RESERVED_END`, writes into the
+// claimed range, then waits for every lower-addressed reservation to commit (`Atomics::wait` on
+// the committed end cursor) before publishing its own. The receiver is unchanged: it still only
+// ever reads against the committed cursor, exactly as in `spsc`.
+//
+// Like `spsc`, this is modeled after https://github.com/willemt/bipbuffer, has not been
+// extensively tested for suitability and/or correctness, and might change at any moment.
+use super::*;
+use js_sys::{Array, Atomics, Int32Array, SharedArrayBuffer, Uint8Array};
+use std::marker::PhantomData;
+use std::time::Duration;
+#[cfg(test)]
+#[allow(unused_imports)]
+use wasm_rs_dbg::dbg;
+
+/// Shared multiple-producer, single-consumer channel
+///
+/// A channel can be passed between different threads with their own instances of a WebAssembly
+/// module by caling [`wasm_bindgen::JsValue::from`] on this channel and subsequently calling
+/// [`SharedChannel::from`] on the value in a different thread.
+pub struct SharedChannel<T>
+where
+    T: Shareable,
+{
+    _header: SharedArrayBuffer,
+    _data: SharedArrayBuffer,
+    header: Int32Array,
+    data: Uint8Array,
+    len: u32,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T> From<SharedChannel<T>> for JsValue
+where
+    T: Shareable,
+{
+    fn from(channel: SharedChannel<T>) -> JsValue {
+        let array = Array::new();
+        array.push(&channel._header);
+        array.push(&channel._data);
+        array.into()
+    }
+}
+
+impl<T> From<JsValue> for SharedChannel<T>
+where
+    T: Shareable,
+{
+    fn from(array: JsValue) -> SharedChannel<T> {
+        let array: Array = array.into();
+        let header = array.shift();
+        let data = array.shift();
+        channel_(header.into(), data.into())
+    }
+}
+
+const A_START: u32 = 0;
+const A_END: u32 = 1;
+const B_END: u32 = 2;
+const B_USE: u32 = 3;
+/// Byte offset up to which producers have *claimed* (but not necessarily written and committed)
+/// space in the currently active region. Mirrors whichever of `A_END`/`B_END` is active; advanced
+/// with a CAS loop so concurrent producers never claim overlapping ranges.
+const RESERVED_END: u32 = 4;
+
+impl<T> SharedChannel<T>
+where
+    T: Shareable,
+{
+    fn maybe_switch(&self) -> Result<(), JsValue> {
+        let a_start = Atomics::load(&self.header, A_START)? as u32;
+        let a_end = Atomics::load(&self.header, A_END)? as u32;
+        let b_end = Atomics::load(&self.header, B_END)? as u32;
+        if self.len - a_end < a_start - b_end {
+            Atomics::store(&self.header, B_USE, 1i32)?;
+            self.advance_reserved_end(b_end)?;
+        }
+        Ok(())
+    }
+
+    /// Advances `RESERVED_END` to `at_least`, retrying via compare-exchange rather than
+    /// overwriting it outright, so it can never move backwards.
+    ///
+    /// `RESERVED_END` tracks the byte offset up to which producers have CAS-claimed space; once a
+    /// producer has claimed a range beyond the committed cursor, moving `RESERVED_END` back below
+    /// that claim (even transiently) would let another producer re-claim and overwrite the same
+    /// bytes. It must only ever be advanced, whether by a producer's own CAS in
+    /// [`Sender::write`](Sender::write) or here, after the receiver or [`Self::maybe_switch`]
+    /// establishes a new committed boundary.
+    fn advance_reserved_end(&self, at_least: u32) -> Result<(), JsValue> {
+        loop {
+            let reserved = Atomics::load(&self.header, RESERVED_END)? as u32;
+            if reserved >= at_least {
+                return Ok(());
+            }
+            let previous = Atomics::compare_exchange(
+                &self.header,
+                RESERVED_END,
+                reserved as i32,
+                at_least as i32,
+            )? as u32;
+            if previous == reserved {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Consumes and splits channel into a [`Sender`] and a [`Receiver`]
+    ///
+    /// Splitting it into allows us to ensure roles aren't mixed up. The [`Sender`] can be
+    /// [`Clone`]d to give several producers their own handle onto the same channel.
+    pub fn split(self) -> (Sender<T>, Receiver<T>) {
+        (Sender(self.clone()), Receiver(self))
+    }
+}
+
+impl<T> Clone for SharedChannel<T>
+where
+    T: Shareable,
+{
+    fn clone(&self) -> Self {
+        Self {
+            _header: self._header.clone(),
+            _data: self._data.clone(),
+            header: self.header.clone(),
+            data: self.data.clone(),
+            len: self.len,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+/// Sender part of the channel
+///
+/// Cloning a [`Sender`] gives another producer its own handle onto the same underlying buffer;
+/// every clone reserves space atomically before writing, so any number of them may call
+/// [`Sender::send`] concurrently from different threads.
+#[derive(Clone)]
+pub struct Sender<T>(pub SharedChannel<T>)
+where
+    T: Shareable;
+
+/// Receiver part of the channel
+pub struct Receiver<T>(pub SharedChannel<T>)
+where
+    T: Shareable;
+
+/// Creates a channel of `len` bytes
+pub fn channel<T>(len: u32) -> SharedChannel<T>
+where
+    T: Shareable,
+{
+    let header = SharedArrayBuffer::new(5 * (std::mem::size_of::<u32>() as u32));
+    let data = SharedArrayBuffer::new(len);
+    channel_(header, data)
+}
+
+fn channel_<T>(header: SharedArrayBuffer, data: SharedArrayBuffer) -> SharedChannel<T>
+where
+    T: Shareable,
+{
+    let header_ = Int32Array::new(&header);
+    let data_ = Uint8Array::new(&data);
+    let len = data_.byte_length();
+    SharedChannel {
+        _header: header,
+        _data: data,
+        header: header_,
+        data: data_,
+        len,
+        phantom_data: PhantomData,
+    }
+}
+
+impl<T> Sender<T>
+where
+    T: Shareable,
+{
+    /// Sends a value into the channel
+    ///
+    /// Safe to call from several cloned [`Sender`]s at once: each call first reserves its byte
+    /// range with a compare-exchange loop, then waits for any lower-addressed reservation to
+    /// commit before publishing its own, so messages are still delivered in a consistent order.
+    ///
+    /// If there isn't enough space currently in the channel to accommodate the value, returns
+    /// [`SendError::Full`] with the value so the caller can retry once space frees up, instead
+    /// of losing it.
+    ///
+    /// Unlike [`spsc::Sender::send`](crate::spsc::Sender::send), this can block the calling
+    /// thread under contention (waiting for a lower-addressed reservation to commit), which
+    /// throws on the browser main thread.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let bytes = match value.to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(SendError::Serialize(value, Box::new(error))),
+        };
+        let len = bytes.byte_length();
+        // Atomics on our own, correctly-sized `SharedArrayBuffer` views aren't expected to fail;
+        // if one somehow does, treat it like "not enough space" so the value isn't lost.
+        match self.write(&bytes, len) {
+            Ok(true) => Ok(()),
+            Ok(false) | Err(_) => Err(SendError::Full(value)),
+        }
+    }
+
+    fn write(&self, bytes: &Uint8Array, len: u32) -> Result<bool, JsValue> {
+        let (start, end_header) = loop {
+            let b_use = (Atomics::load(&self.0.header, B_USE)? as u32) == 1;
+            let end_header = if b_use { B_END } else { A_END };
+            let reserved = Atomics::load(&self.0.header, RESERVED_END)? as u32;
+            // Bound checked against this very `reserved` read, right before the CAS that commits
+            // to it: checking against an earlier snapshot would let another producer advance
+            // `RESERVED_END` in between, so the CAS below could still succeed while claiming a
+            // range that overruns the active region.
+            let boundary = if b_use {
+                Atomics::load(&self.0.header, A_START)? as u32
+            } else {
+                self.0.len
+            };
+            if reserved + len > boundary {
+                return Ok(false);
+            }
+
+            let claimed = Atomics::compare_exchange(
+                &self.0.header,
+                RESERVED_END,
+                reserved as i32,
+                (reserved + len) as i32,
+            )? as u32;
+            if claimed == reserved {
+                break (reserved, end_header);
+            }
+            // Another producer won the race for this range; retry against the new cursor.
+        };
+
+        for i in 0..len {
+            self.0.data.set_index(start + i, bytes.get_index(i));
+        }
+
+        // Only publish once every lower-addressed reservation has committed, so the committed
+        // cursor the receiver reads always advances contiguously.
+        loop {
+            let committed = Atomics::load(&self.0.header, end_header)? as u32;
+            if committed == start {
+                break;
+            }
+            Atomics::wait(&self.0.header, end_header, committed as i32)?;
+        }
+
+        Atomics::store(&self.0.header, end_header, (start + len) as i32)?;
+        Atomics::notify(&self.0.header, end_header)?;
+        Atomics::notify(&self.0.header, A_START)?;
+
+        self.0.maybe_switch()?;
+
+        Ok(true)
+    }
+}
+
+impl<T> Receiver<T>
+where
+    T: Shareable,
+{
+    /// Advances the header cursors past `sz` now-consumed bytes (handling the A/B switch)
+    ///
+    /// Shared by every receive method; callers must only invoke this once they've confirmed a
+    /// full message was read, since it's not safe to un-consume bytes once committed. Mirrors the
+    /// equivalent helper in `spsc`, except it also has to bump `RESERVED_END` forward on a
+    /// switch, since producers may already have claimed space in what just became the active
+    /// region.
+    fn advance_read_cursors(
+        &self,
+        mut a_start: u32,
+        mut a_end: u32,
+        sz: u32,
+    ) -> Result<(), JsValue> {
+        a_start += sz;
+        let mut b_end = Atomics::load(&self.0.header, B_END)? as u32;
+        let mut b_use = (Atomics::load(&self.0.header, B_USE)? as u32) == 1;
+        if a_start == a_end {
+            if b_use {
+                a_start = 0;
+                a_end = b_end;
+                b_end = 0;
+                b_use = false;
+            } else {
+                a_start = 0;
+                a_end = 0;
+            }
+        }
+        Atomics::store(&self.0.header, B_USE, if b_use { 1i32 } else { 0i32 })?;
+        Atomics::store(&self.0.header, A_START, a_start as i32)?;
+        Atomics::store(&self.0.header, A_END, a_end as i32)?;
+        Atomics::store(&self.0.header, B_END, b_end as i32)?;
+        if !b_use {
+            // Bump, don't overwrite: producers may already have CAS-claimed (and be mid-write
+            // on) a range beyond the old committed boundary in what is now the active region,
+            // and must not have it reclaimed out from under them.
+            self.0.advance_reserved_end(a_end)?;
+        }
+        self.0.maybe_switch()?;
+        Atomics::notify(&self.0.header, B_END)?;
+        Ok(())
+    }
+
+    /// Copies `sz` readable bytes starting at `a_start` into `array`, then advances the header
+    /// cursors if that completes a full message
+    fn copy_and_advance(
+        &self,
+        array: &Uint8Array,
+        a_start: u32,
+        a_end: u32,
+        sz: u32,
+    ) -> Result<(), JsValue> {
+        for i in 0..sz {
+            array.set_index(i, self.0.data.get_index(a_start + i));
+        }
+        if T::from(array)
+            .map_err(|e| JsValue::from(format!("deserialization error: {}", e)))?
+            .is_ok()
+        {
+            self.advance_read_cursors(a_start, a_end, sz)?;
+        }
+        Ok(())
+    }
+
+    /// Receives a value from the channel
+    ///
+    /// This is a convenience wrapper over [`Receiver::try_recv`]/[`Receiver::recv_timeout`] for
+    /// callers that don't need to distinguish "empty"/"timed out" from a deserialization
+    /// failure; use those directly to match on the specific error.
+    pub fn recv(&self, timeout: Option<Duration>) -> Result<Option<T>, JsValue> {
+        match timeout {
+            None => match self.try_recv() {
+                Ok(value) => Ok(Some(value)),
+                Err(RecvError::Empty) => Ok(None),
+                Err(error) => Err(error.into()),
+            },
+            Some(duration) => match self.recv_timeout(duration) {
+                Ok(value) => Ok(Some(value)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(error) => Err(error.into()),
+            },
+        }
+    }
+
+    /// Receives a value from the channel without blocking
+    ///
+    /// Returns [`RecvError::Empty`] immediately if there is no message ready yet. Behaves
+    /// exactly as [`spsc::Receiver::try_recv`](crate::spsc::Receiver::try_recv): it only ever
+    /// reads against the committed end cursor, so it doesn't need to know or care how many
+    /// producers are sending.
+    pub fn try_recv(&self) -> Result<T, RecvError> {
+        let mut array = Uint8Array::new_with_length(0);
+        loop {
+            match T::from(&array).map_err(|e| RecvError::Deserialize(e.to_string()))? {
+                Ok(value) => return Ok(value),
+                Err(Expects(sz)) => {
+                    array = Uint8Array::new_with_length(sz);
+                    let a_start = Atomics::load(&self.0.header, A_START)? as u32;
+                    let a_end = Atomics::load(&self.0.header, A_END)? as u32;
+                    if a_start == a_end || self.0.len < a_start + sz {
+                        return Err(RecvError::Empty);
+                    }
+                    self.copy_and_advance(&array, a_start, a_end, sz)?;
+                }
+            }
+        }
+    }
+
+    /// Receives a value from the channel, blocking the calling thread with `Atomics.wait` for up
+    /// to `timeout` if there isn't one ready yet
+    ///
+    /// Returns [`RecvTimeoutError::Timeout`] if no message arrives in time. Behaves exactly as
+    /// [`spsc::Receiver::recv_timeout`](crate::spsc::Receiver::recv_timeout): it only ever reads
+    /// against the committed end cursor, so it doesn't need to know or care how many producers
+    /// are sending.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let mut array = Uint8Array::new_with_length(0);
+        loop {
+            match T::from(&array).map_err(|e| RecvTimeoutError::Deserialize(e.to_string()))? {
+                Ok(value) => return Ok(value),
+                Err(Expects(sz)) => {
+                    array = Uint8Array::new_with_length(sz);
+                    let a_start = Atomics::load(&self.0.header, A_START)? as u32;
+                    let a_end = Atomics::load(&self.0.header, A_END)? as u32;
+                    if a_start == a_end || self.0.len < a_start + sz {
+                        let result = Atomics::wait_with_timeout(
+                            &self.0.header,
+                            A_START,
+                            a_start as i32,
+                            timeout.as_millis() as f64,
+                        )?;
+                        if result == "timed-out" {
+                            return Err(RecvTimeoutError::Timeout);
+                        }
+                        continue;
+                    }
+                    self.copy_and_advance(&array, a_start, a_end, sz)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (sender, receiver) = channel::<u8>(2 * sz).split();
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        assert_eq!(receiver.recv(None).unwrap().unwrap(), 1);
+        assert_eq!(receiver.recv(None).unwrap().unwrap(), 2);
+    }
+
+    #[wasm_bindgen_test]
+    fn not_enough_space() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (sender, _receiver) = channel::<u8>(1 * sz).split();
+        sender.send(1).unwrap();
+        assert!(sender.send(2).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn cloned_senders() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (sender, receiver) = channel::<u8>(4 * sz).split();
+        let other = sender.clone();
+        sender.send(1).unwrap();
+        other.send(2).unwrap();
+        assert_eq!(receiver.recv(None).unwrap().unwrap(), 1);
+        assert_eq!(receiver.recv(None).unwrap().unwrap(), 2);
+    }
+
+    #[wasm_bindgen_test]
+    fn try_recv_empty() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (_sender, receiver) = channel::<u8>(sz).split();
+        assert!(matches!(receiver.try_recv(), Err(RecvError::Empty)));
+    }
+
+    #[wasm_bindgen_test]
+    fn recv_timeout_times_out() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (_sender, receiver) = channel::<u8>(sz).split();
+        assert!(matches!(
+            receiver.recv_timeout(std::time::Duration::from_millis(50)),
+            Err(RecvTimeoutError::Timeout)
+        ));
+    }
+}
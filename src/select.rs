@@ -0,0 +1,135 @@
+//! # Multiplexing across several receivers
+//!
+//! [`Selector`] lets a single worker service several [`spsc::Receiver`]s (e.g. a control channel
+//! and a data channel) from one loop, instead of polling each one with a tiny timeout.
+use crate::spsc::Receiver;
+use crate::{RecvError, Shareable};
+use js_sys::Date;
+use std::time::Duration;
+use wasm_bindgen::JsValue;
+
+/// Waits on whichever of several registered receivers becomes ready first
+///
+/// Each call to [`Selector::select`] probes every registered receiver once for an immediately
+/// decodable message; if none is ready, it parks on `Atomics.wait` against one of them for the
+/// remaining timeout (the receiver picked rotates between calls, so repeated selects don't
+/// always favor the same one), then re-probes all of them once woken.
+///
+/// This blocks the calling thread, which throws on the browser main thread.
+pub struct Selector<T>
+where
+    T: Shareable,
+{
+    receivers: Vec<Receiver<T>>,
+    next: usize,
+}
+
+impl<T> Selector<T>
+where
+    T: Shareable,
+{
+    /// Creates an empty selector; register receivers with [`Selector::add`]
+    pub fn new() -> Self {
+        Selector {
+            receivers: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Registers a receiver, returning the index [`Selector::select`] will report it under
+    pub fn add(&mut self, receiver: Receiver<T>) -> usize {
+        self.receivers.push(receiver);
+        self.receivers.len() - 1
+    }
+
+    /// Waits for any registered receiver to have a decodable message
+    ///
+    /// Returns the index of the receiver the value came from (as returned by [`Selector::add`])
+    /// together with the value itself, or `Ok(None)` if `timeout` elapses first. If `timeout` is
+    /// `None` and no registered receiver has a message ready, returns `Ok(None)` immediately. If
+    /// no receiver has been registered, always returns `Ok(None)`.
+    pub fn select(&mut self, timeout: Option<Duration>) -> Result<Option<(usize, T)>, JsValue> {
+        if self.receivers.is_empty() {
+            return Ok(None);
+        }
+        let deadline = timeout.map(|timeout| Date::now() + timeout.as_millis() as f64);
+        loop {
+            for offset in 0..self.receivers.len() {
+                let index = (self.next + offset) % self.receivers.len();
+                match self.receivers[index].try_recv() {
+                    Ok(value) => {
+                        self.next = (index + 1) % self.receivers.len();
+                        return Ok(Some((index, value)));
+                    }
+                    Err(RecvError::Empty) => {}
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            let remaining = match deadline {
+                None => return Ok(None),
+                Some(deadline) => deadline - Date::now(),
+            };
+            if remaining <= 0.0 {
+                return Ok(None);
+            }
+            let duration = Duration::from_millis(remaining as u64);
+            let waiting = self.next;
+            self.next = (self.next + 1) % self.receivers.len();
+            let current = self.receivers[waiting].a_start()?;
+            if self.receivers[waiting].wait_a_start(current, duration)? == "timed-out" {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+impl<T> Default for Selector<T>
+where
+    T: Shareable,
+{
+    fn default() -> Self {
+        Selector::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    use super::*;
+    use crate::spsc::channel;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn selects_whichever_is_ready() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (sender_a, receiver_a) = channel::<u8>(2 * sz).split();
+        let (sender_b, receiver_b) = channel::<u8>(2 * sz).split();
+        let mut selector = Selector::new();
+        let a = selector.add(receiver_a);
+        let b = selector.add(receiver_b);
+
+        sender_b.send(2).unwrap();
+        assert_eq!(selector.select(None).unwrap().unwrap(), (b, 2));
+
+        sender_a.send(1).unwrap();
+        assert_eq!(selector.select(None).unwrap().unwrap(), (a, 1));
+
+        assert!(selector.select(None).unwrap().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn select_times_out() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (_sender_a, receiver_a) = channel::<u8>(sz).split();
+        let (_sender_b, receiver_b) = channel::<u8>(sz).split();
+        let mut selector = Selector::new();
+        selector.add(receiver_a);
+        selector.add(receiver_b);
+
+        assert!(selector
+            .select(Some(std::time::Duration::from_millis(50)))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -10,7 +10,7 @@
 // This is an ongoing area of development and the algorithm might change at any moment, so
 // one should not base their expectations based on the particularities of the algorithm.
 use super::*;
-use js_sys::{Array, Atomics, Int32Array, SharedArrayBuffer, Uint8Array};
+use js_sys::{Array, Atomics, Date, Int32Array, SharedArrayBuffer, Uint8Array};
 use std::marker::PhantomData;
 use std::time::Duration;
 #[cfg(test)]
@@ -157,15 +157,26 @@ where
 {
     /// Sends a value into the channel
     ///
-    /// If there isn't enough space currently in the channel to accommodate
-    /// the value, it'll throw a JavaScript exception (`"not enough space"`)
-    pub fn send(&self, value: &T) -> Result<(), JsValue> {
-        let bytes = value
-            .to_bytes()
-            .map_err(|e| JsValue::from(format!("serialization error: {}", e)))?;
+    /// If there isn't enough space currently in the channel to accommodate the value, returns
+    /// [`SendError::Full`] with the value so the caller can retry once space frees up, instead
+    /// of losing it.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let bytes = match value.to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(SendError::Serialize(value, Box::new(error))),
+        };
         let len = bytes.byte_length();
+        // Atomics on our own, correctly-sized `SharedArrayBuffer` views aren't expected to fail;
+        // if one somehow does, treat it like "not enough space" so the value isn't lost.
+        match self.write(&bytes, len) {
+            Ok(true) => Ok(()),
+            Ok(false) | Err(_) => Err(SendError::Full(value)),
+        }
+    }
+
+    fn write(&self, bytes: &Uint8Array, len: u32) -> Result<bool, JsValue> {
         if self.0.unused()? < len {
-            return Err("not enough space".to_string().into());
+            return Ok(false);
         }
         let b_use = (Atomics::load(&self.0.header, B_USE)? as u32) == 1;
         let end_header = if b_use { B_END } else { A_END };
@@ -180,7 +191,57 @@ where
 
         self.0.maybe_switch()?;
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// Sends a value into the channel, applying back-pressure instead of failing when it's full
+    ///
+    /// If there isn't enough space, parks the calling thread on `Atomics.wait` until the receiver
+    /// advances `A_START` (i.e. reads a message and frees up space) or `timeout` elapses,
+    /// whichever comes first, then retries. If `timeout` is `None`, behaves like [`Sender::send`]
+    /// and gives up the first time the channel is full.
+    ///
+    /// This blocks the calling thread, which throws on the browser main thread.
+    pub fn send_blocking(
+        &self,
+        value: T,
+        timeout: Option<Duration>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        let bytes = match value.to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(SendTimeoutError::Serialize(value, Box::new(error))),
+        };
+        let len = bytes.byte_length();
+        // Tracked as a deadline, not re-used as-is on every retry: otherwise each wakeup that
+        // still doesn't leave enough contiguous space (e.g. the receiver is draining smaller
+        // messages one at a time) would park for the *full* timeout again, making the total
+        // blocking time unbounded multiples of it instead of honoring it as a whole.
+        let deadline = timeout.map(|timeout| Date::now() + timeout.as_millis() as f64);
+        loop {
+            match self.write(&bytes, len) {
+                Ok(true) => return Ok(()),
+                Err(_) => return Err(SendTimeoutError::Timeout(value)),
+                Ok(false) => {}
+            }
+            let remaining = match deadline {
+                None => return Err(SendTimeoutError::Timeout(value)),
+                Some(deadline) => deadline - Date::now(),
+            };
+            if remaining <= 0.0 {
+                return Err(SendTimeoutError::Timeout(value));
+            }
+            let a_start = match Atomics::load(&self.0.header, A_START) {
+                Ok(a_start) => a_start,
+                Err(_) => return Err(SendTimeoutError::Timeout(value)),
+            };
+            match Atomics::wait_with_timeout(&self.0.header, A_START, a_start, remaining) {
+                Ok(result) if result == "timed-out" => {
+                    return Err(SendTimeoutError::Timeout(value))
+                }
+                Ok(_) => {}
+                Err(_) => return Err(SendTimeoutError::Timeout(value)),
+            }
+        }
     }
 }
 
@@ -188,6 +249,82 @@ impl<T> Receiver<T>
 where
     T: Shareable,
 {
+    /// Current value of the `A_START` header slot
+    ///
+    /// Used by [`crate::select::Selector`] to `Atomics.wait` against this receiver alongside
+    /// several others.
+    pub(crate) fn a_start(&self) -> Result<i32, JsValue> {
+        Atomics::load(&self.0.header, A_START)
+    }
+
+    /// Parks the calling thread on `Atomics.wait` until `A_START` changes from `current` or
+    /// `timeout` elapses
+    ///
+    /// Used by [`crate::select::Selector`], which already knows `current` from
+    /// [`Receiver::a_start`] and only needs to block on it.
+    pub(crate) fn wait_a_start(&self, current: i32, timeout: Duration) -> Result<String, JsValue> {
+        Atomics::wait_with_timeout(&self.0.header, A_START, current, timeout.as_millis() as f64)
+    }
+
+    /// Advances the header cursors past `sz` now-consumed bytes (handling the A/B switch), and
+    /// wakes any sender parked in [`Sender::send_blocking`] waiting for the space to free up.
+    ///
+    /// Shared by every receive method; callers must only invoke this once they've confirmed a
+    /// full message was read, since it's not safe to un-consume bytes once committed.
+    fn advance_read_cursors(
+        &self,
+        mut a_start: u32,
+        mut a_end: u32,
+        sz: u32,
+    ) -> Result<(), JsValue> {
+        a_start += sz;
+        let mut b_end = Atomics::load(&self.0.header, B_END)? as u32;
+        let mut b_use = (Atomics::load(&self.0.header, B_USE)? as u32) == 1;
+        if a_start == a_end {
+            if b_use {
+                a_start = 0;
+                a_end = b_end;
+                b_end = 0;
+                b_use = false;
+            } else {
+                a_start = 0;
+                a_end = 0;
+            }
+        }
+        Atomics::store(&self.0.header, B_USE, if b_use { 1i32 } else { 0i32 })?;
+        Atomics::store(&self.0.header, A_START, a_start as i32)?;
+        Atomics::store(&self.0.header, A_END, a_end as i32)?;
+        Atomics::store(&self.0.header, B_END, b_end as i32)?;
+        self.0.maybe_switch()?;
+        // Wake any sender parked in `Sender::send_blocking`, waiting for space to free up.
+        Atomics::notify(&self.0.header, A_START)?;
+        Ok(())
+    }
+
+    /// Copies `sz` readable bytes starting at `a_start` into `array`, then advances the header
+    /// cursors if that completes a full message.
+    ///
+    /// Shared by [`Receiver::recv`] and [`Receiver::recv_async`], which only differ in how they
+    /// wait for a message to become available.
+    fn copy_and_advance(
+        &self,
+        array: &Uint8Array,
+        a_start: u32,
+        a_end: u32,
+        sz: u32,
+    ) -> Result<(), JsValue> {
+        for i in 0..sz {
+            array.set_index(i, self.0.data.get_index(a_start + i));
+        }
+        if T::from(array)
+            .map_err(|e| JsValue::from(format!("deserialization error: {}", e)))?
+            .is_ok()
+        {
+            self.advance_read_cursors(a_start, a_end, sz)?;
+        }
+        Ok(())
+    }
+
     /// Receives a value from the channel
     ///
     /// If `timeout` is `None`, if there is no message, it'll immediately return
@@ -198,7 +335,162 @@ where
     ///
     /// There's no way to specify an infinite timeout. Instead, a sufficiently large
     /// [`std::time::Duration`] should be used.
+    ///
+    /// This blocks the calling thread with `Atomics.wait`, which throws on the browser main
+    /// thread. Use [`Receiver::recv_async`] there instead.
+    ///
+    /// This is a convenience wrapper over [`Receiver::try_recv`]/[`Receiver::recv_timeout`] for
+    /// callers that don't need to distinguish "empty"/"timed out" from a deserialization
+    /// failure; use those directly to match on the specific error.
     pub fn recv(&self, timeout: Option<Duration>) -> Result<Option<T>, JsValue> {
+        match timeout {
+            None => match self.try_recv() {
+                Ok(value) => Ok(Some(value)),
+                Err(RecvError::Empty) => Ok(None),
+                Err(error) => Err(error.into()),
+            },
+            Some(duration) => match self.recv_timeout(duration) {
+                Ok(value) => Ok(Some(value)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(error) => Err(error.into()),
+            },
+        }
+    }
+
+    /// Receives a value from the channel without blocking
+    ///
+    /// Returns [`RecvError::Empty`] immediately if there is no message ready yet.
+    pub fn try_recv(&self) -> Result<T, RecvError> {
+        let mut array = Uint8Array::new_with_length(0);
+        loop {
+            match T::from(&array).map_err(|e| RecvError::Deserialize(e.to_string()))? {
+                Ok(value) => return Ok(value),
+                Err(Expects(sz)) => {
+                    array = Uint8Array::new_with_length(sz);
+                    let a_start = Atomics::load(&self.0.header, A_START)? as u32;
+                    let a_end = Atomics::load(&self.0.header, A_END)? as u32;
+                    if a_start == a_end || self.0.len < a_start + sz {
+                        return Err(RecvError::Empty);
+                    }
+                    self.copy_and_advance(&array, a_start, a_end, sz)?;
+                }
+            }
+        }
+    }
+
+    /// Receives a value from the channel, blocking the calling thread with `Atomics.wait` for up
+    /// to `timeout` if there isn't one ready yet
+    ///
+    /// Returns [`RecvTimeoutError::Timeout`] if no message arrives in time. This blocks the
+    /// calling thread, which throws on the browser main thread; use [`Receiver::recv_async`]
+    /// there instead.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let mut array = Uint8Array::new_with_length(0);
+        loop {
+            match T::from(&array).map_err(|e| RecvTimeoutError::Deserialize(e.to_string()))? {
+                Ok(value) => return Ok(value),
+                Err(Expects(sz)) => {
+                    array = Uint8Array::new_with_length(sz);
+                    let a_start = Atomics::load(&self.0.header, A_START)? as u32;
+                    let a_end = Atomics::load(&self.0.header, A_END)? as u32;
+                    if a_start == a_end || self.0.len < a_start + sz {
+                        let result = Atomics::wait_with_timeout(
+                            &self.0.header,
+                            A_START,
+                            a_start as i32,
+                            timeout.as_millis() as f64,
+                        )?;
+                        if result == "timed-out" {
+                            return Err(RecvTimeoutError::Timeout);
+                        }
+                        continue;
+                    }
+                    self.copy_and_advance(&array, a_start, a_end, sz)?;
+                }
+            }
+        }
+    }
+
+    /// Receives up to `max` messages in a single pass, advancing `A_START` once at the end
+    /// instead of once per message
+    ///
+    /// Waits for at least one message to become available the same way [`Receiver::recv`] does
+    /// (giving up after `timeout`, or immediately if `timeout` is `None`), then decodes further
+    /// messages directly out of the same contiguous readable region, without re-checking the
+    /// header in between, until it either collects `max` of them, runs out of readable bytes, or
+    /// would have to cross the bipbuffer's A/B switch boundary to read the next one. This
+    /// amortizes the per-message `Atomics` load/store overhead of calling [`Receiver::recv`] in a
+    /// loop across however many small messages end up batched together.
+    ///
+    /// This blocks the calling thread with `Atomics.wait`, which throws on the browser main
+    /// thread. Use [`Receiver::recv_async`] there instead.
+    pub fn recv_batch(&self, max: usize, timeout: Option<Duration>) -> Result<Vec<T>, JsValue> {
+        if max == 0 {
+            return Ok(Vec::new());
+        }
+        let (a_start, a_end) = loop {
+            let a_start = Atomics::load(&self.0.header, A_START)? as u32;
+            let a_end = Atomics::load(&self.0.header, A_END)? as u32;
+            if a_start != a_end {
+                break (a_start, a_end);
+            }
+            match timeout {
+                None => return Ok(Vec::new()),
+                Some(duration) => {
+                    let result = Atomics::wait_with_timeout(
+                        &self.0.header,
+                        A_START,
+                        a_start as i32,
+                        duration.as_millis() as f64,
+                    )?;
+                    if result == "timed-out" {
+                        return Ok(Vec::new());
+                    }
+                }
+            }
+        };
+
+        let mut values = Vec::new();
+        let mut offset = a_start;
+        'batch: while values.len() < max {
+            let mut array = Uint8Array::new_with_length(0);
+            let sz: u32 = loop {
+                match T::from(&array)
+                    .map_err(|e| JsValue::from(format!("deserialization error: {}", e)))?
+                {
+                    Ok(value) => {
+                        values.push(value);
+                        break array.byte_length();
+                    }
+                    Err(Expects(sz)) => {
+                        if offset + sz > a_end {
+                            break 'batch;
+                        }
+                        array = Uint8Array::new_with_length(sz);
+                        for i in 0..sz {
+                            array.set_index(i, self.0.data.get_index(offset + i));
+                        }
+                    }
+                }
+            };
+            offset += sz;
+        }
+
+        if offset > a_start {
+            self.advance_read_cursors(a_start, a_end, offset - a_start)?;
+        }
+        Ok(values)
+    }
+
+    /// Receives a value from the channel without ever blocking the calling thread.
+    ///
+    /// Mirrors [`Receiver::recv`]'s state machine, but waits for a message with
+    /// `Atomics.waitAsync` instead of `Atomics.wait`, so it's safe to call from the browser main
+    /// thread (e.g. from an `async` event handler), where blocking waits throw.
+    ///
+    /// Resolves to `Ok(Some(value))` once a value is available, `Ok(None)` if `timeout` elapses
+    /// first, and immediately with `Ok(None)` if `timeout` is `None` and there is no message.
+    pub async fn recv_async(&self, timeout: Option<Duration>) -> Result<Option<T>, JsValue> {
         let mut array = Uint8Array::new_with_length(0);
         loop {
             match T::from(&array)
@@ -209,8 +501,90 @@ where
                 }
                 Err(Expects(sz)) => {
                     array = Uint8Array::new_with_length(sz);
-                    let mut a_start = Atomics::load(&self.0.header, A_START)? as u32;
-                    let mut a_end = Atomics::load(&self.0.header, A_END)? as u32;
+                    let a_start = Atomics::load(&self.0.header, A_START)? as u32;
+                    let a_end = Atomics::load(&self.0.header, A_END)? as u32;
+                    if a_start == a_end || self.0.len < a_start + sz {
+                        match timeout {
+                            None => return Ok(None),
+                            Some(duration) => {
+                                let result = self.wait_async(a_start, duration).await?;
+                                if result == "timed-out" {
+                                    return Ok(None);
+                                }
+                                continue;
+                            }
+                        }
+                    }
+                    self.copy_and_advance(&array, a_start, a_end, sz)?;
+                }
+            }
+        }
+    }
+
+    /// Awaits `Atomics.waitAsync` on `A_START`, resolving once the value there changes or
+    /// `duration` elapses, with the same `"ok"`/`"not-equal"`/`"timed-out"` result strings as
+    /// `Atomics.wait`.
+    async fn wait_async(&self, a_start: u32, duration: Duration) -> Result<String, JsValue> {
+        let outcome = Atomics::wait_async_with_timeout(
+            &self.0.header,
+            A_START,
+            a_start as i32,
+            duration.as_millis() as f64,
+        )?;
+        if js_sys::Reflect::get(&outcome, &"async".into())?
+            .as_bool()
+            .unwrap_or(false)
+        {
+            let promise: js_sys::Promise = js_sys::Reflect::get(&outcome, &"value".into())?.into();
+            let result = wasm_bindgen_futures::JsFuture::from(promise).await?;
+            Ok(result.as_string().unwrap_or_default())
+        } else {
+            let value = js_sys::Reflect::get(&outcome, &"value".into())?;
+            Ok(value.as_string().unwrap_or_default())
+        }
+    }
+}
+
+/// Owns the raw bytes for one message received via [`Receiver::recv_ref`]
+///
+/// Call [`RecvBytes::get`] to deserialize a value that borrows directly from these bytes (e.g.
+/// `&str`/`&[u8]` fields marked `#[serde(borrow)]`) instead of allocating a copy of each one, the
+/// way [`Receiver::recv`] does.
+pub struct RecvBytes(Vec<u8>);
+
+impl RecvBytes {
+    /// Deserializes the received message, borrowing from the bytes held here
+    pub fn get<'a, T>(&'a self) -> Result<T, T::Error>
+    where
+        T: ShareableRef<'a>,
+    {
+        match T::from_ref(&self.0)? {
+            Ok(value) => Ok(value),
+            Err(_) => unreachable!("Receiver::recv_ref only ever returns a complete message"),
+        }
+    }
+}
+
+impl<T> Receiver<T>
+where
+    T: Shareable + for<'a> ShareableRef<'a>,
+{
+    /// Receives a value from the channel without copying each of its borrowed fields into their
+    /// own allocation
+    ///
+    /// Behaves like [`Receiver::recv`], but returns the raw message bytes wrapped in
+    /// [`RecvBytes`] instead of an already-deserialized `T`; call [`RecvBytes::get`] on the
+    /// result to deserialize, borrowing directly from those bytes.
+    pub fn recv_ref(&self, timeout: Option<Duration>) -> Result<Option<RecvBytes>, JsValue> {
+        let mut bytes: Vec<u8> = Vec::new();
+        loop {
+            match T::from_ref(&bytes)
+                .map_err(|e| JsValue::from(format!("deserialization error: {}", e)))?
+            {
+                Ok(_) => return Ok(Some(RecvBytes(bytes))),
+                Err(Expects(sz)) => {
+                    let a_start = Atomics::load(&self.0.header, A_START)? as u32;
+                    let a_end = Atomics::load(&self.0.header, A_END)? as u32;
                     if a_start == a_end || self.0.len < a_start + sz {
                         match timeout {
                             None => return Ok(None),
@@ -228,32 +602,15 @@ where
                             }
                         }
                     }
+                    bytes = vec![0u8; sz as usize];
                     for i in 0..sz {
-                        array.set_index(i, self.0.data.get_index(a_start + i));
+                        bytes[i as usize] = self.0.data.get_index(a_start + i);
                     }
-                    a_start += sz;
-                    let mut b_end = Atomics::load(&self.0.header, B_END)? as u32;
-                    let mut b_use = (Atomics::load(&self.0.header, B_USE)? as u32) == 1;
-                    if a_start == a_end {
-                        if b_use {
-                            a_start = 0;
-                            a_end = b_end;
-                            b_end = 0;
-                            b_use = false;
-                        } else {
-                            a_start = 0;
-                            a_end = 0;
-                        }
-                    }
-                    if T::from(&array)
+                    if T::from_ref(&bytes)
                         .map_err(|e| JsValue::from(format!("deserialization error: {}", e)))?
                         .is_ok()
                     {
-                        Atomics::store(&self.0.header, B_USE, if b_use { 1i32 } else { 0i32 })?;
-                        Atomics::store(&self.0.header, A_START, a_start as i32)?;
-                        Atomics::store(&self.0.header, A_END, a_end as i32)?;
-                        Atomics::store(&self.0.header, B_END, b_end as i32)?;
-                        self.0.maybe_switch()?;
+                        self.advance_read_cursors(a_start, a_end, sz)?;
                     }
                 }
             }
@@ -272,8 +629,8 @@ mod tests {
     fn test() {
         let sz = 0u8.to_bytes().unwrap().byte_length();
         let (sender, receiver) = channel::<u8>(2 * sz).split();
-        sender.send(&1).unwrap();
-        sender.send(&2).unwrap();
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 1);
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 2);
     }
@@ -282,28 +639,49 @@ mod tests {
     fn not_enough_space() {
         let sz = 0u8.to_bytes().unwrap().byte_length();
         let (sender, _receiver) = channel::<u8>(1 * sz).split();
-        sender.send(&1).unwrap();
-        assert!(sender.send(&2).is_err());
+        sender.send(1).unwrap();
+        match sender.send(2) {
+            Err(SendError::Full(value)) => assert_eq!(value, 2),
+            _ => panic!("expected SendError::Full"),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn send_blocking_gives_up_without_timeout() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (sender, _receiver) = channel::<u8>(1 * sz).split();
+        sender.send_blocking(1, None).unwrap();
+        match sender.send_blocking(2, None) {
+            Err(SendTimeoutError::Timeout(value)) => assert_eq!(value, 2),
+            _ => panic!("expected SendTimeoutError::Timeout"),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn try_recv_empty() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (_sender, receiver) = channel::<u8>(2 * sz).split();
+        assert!(matches!(receiver.try_recv(), Err(RecvError::Empty)));
     }
 
     #[wasm_bindgen_test]
     fn circular() {
         let sz = 0u8.to_bytes().unwrap().byte_length();
         let (sender, receiver) = channel::<u8>(8 * sz).split();
-        sender.send(&1).unwrap();
-        sender.send(&2).unwrap();
-        sender.send(&3).unwrap();
-        sender.send(&4).unwrap();
-        sender.send(&5).unwrap();
-        sender.send(&6).unwrap();
-        sender.send(&7).unwrap();
-        sender.send(&8).unwrap();
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        sender.send(3).unwrap();
+        sender.send(4).unwrap();
+        sender.send(5).unwrap();
+        sender.send(6).unwrap();
+        sender.send(7).unwrap();
+        sender.send(8).unwrap();
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 1);
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 2);
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 3);
-        sender.send(&9).unwrap();
-        sender.send(&10).unwrap();
-        sender.send(&11).unwrap();
+        sender.send(9).unwrap();
+        sender.send(10).unwrap();
+        sender.send(11).unwrap();
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 4);
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 5);
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 6);
@@ -321,9 +699,40 @@ mod tests {
         let js_value: JsValue = ch.into();
         let ch: SharedChannel<u8> = js_value.into();
         let (sender, receiver) = ch.split();
-        sender.send(&1).unwrap();
-        sender.send(&2).unwrap();
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 1);
         assert_eq!(receiver.recv(None).unwrap().unwrap(), 2);
     }
+
+    #[wasm_bindgen_test]
+    async fn recv_async() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (sender, receiver) = channel::<u8>(2 * sz).split();
+        sender.send(1).unwrap();
+        assert_eq!(receiver.recv_async(None).await.unwrap().unwrap(), 1);
+        assert_eq!(receiver.recv_async(None).await.unwrap(), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn recv_ref() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (sender, receiver) = channel::<u8>(2 * sz).split();
+        sender.send(1).unwrap();
+        let received = receiver.recv_ref(None).unwrap().unwrap();
+        assert_eq!(received.get::<u8>().unwrap(), 1);
+        assert!(receiver.recv_ref(None).unwrap().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn recv_batch() {
+        let sz = 0u8.to_bytes().unwrap().byte_length();
+        let (sender, receiver) = channel::<u8>(4 * sz).split();
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        sender.send(3).unwrap();
+        assert_eq!(receiver.recv_batch(2, None).unwrap(), vec![1, 2]);
+        assert_eq!(receiver.recv_batch(2, None).unwrap(), vec![3]);
+        assert!(receiver.recv_batch(2, None).unwrap().is_empty());
+    }
 }